@@ -0,0 +1,22 @@
+use crate::cli::AuthType;
+use crate::url::Url;
+
+pub enum Auth {
+    Bearer(String),
+    Basic(String, Option<String>),
+}
+
+impl Auth {
+    pub fn new(auth: Option<String>, auth_type: AuthType, _url: &Url) -> Option<Auth> {
+        let auth = auth?;
+        match auth_type {
+            AuthType::Bearer => Some(Auth::Bearer(auth)),
+            AuthType::Basic => {
+                let mut parts = auth.splitn(2, ':');
+                let username = parts.next().unwrap_or_default().to_string();
+                let password = parts.next().map(|p| p.to_string());
+                Some(Auth::Basic(username, password))
+            }
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use std::ffi::OsString;
+use std::str::FromStr;
+use std::time::Duration;
+
+use reqwest::Method;
+use structopt::StructOpt;
+
+use crate::request_items::RequestItem;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "xh")]
+pub struct Opt {
+    /// The HTTP method to use.
+    #[structopt(name = "METHOD")]
+    pub method: CliMethod,
+
+    /// The URL to request.
+    #[structopt(name = "URL")]
+    pub url: String,
+
+    /// Optional key-value pairs to be included in the request.
+    #[structopt(name = "REQUEST_ITEM")]
+    pub request_items: Vec<RequestItem>,
+
+    /// The default scheme to use if not specified in the URL.
+    #[structopt(long)]
+    pub default_scheme: Option<String>,
+
+    /// Controls output processing.
+    #[structopt(long, possible_values = &["all", "colors", "format", "none"])]
+    pub pretty: Option<Pretty>,
+
+    /// Output coloring style.
+    #[structopt(long = "style", possible_values = &["auto", "solarized"])]
+    pub theme: Option<Theme>,
+
+    /// Controls what the output should contain.
+    #[structopt(short = "p", long)]
+    pub print: Option<Print>,
+
+    /// Print the whole request as well as the response.
+    #[structopt(short = "v", long)]
+    pub verbose: bool,
+
+    /// Submit the request as form data.
+    #[structopt(short = "f", long)]
+    pub form: bool,
+
+    /// Submit the request as multipart/form-data.
+    #[structopt(long)]
+    pub multipart: bool,
+
+    /// Authenticate as USER with PASS, or with a bearer TOKEN.
+    #[structopt(short = "a", long)]
+    pub auth: Option<String>,
+
+    /// Specify the auth mechanism.
+    #[structopt(long = "auth-type", possible_values = &["basic", "bearer"], default_value = "basic")]
+    pub auth_type: AuthType,
+
+    /// Do not attempt to read stdin.
+    #[structopt(short = "I", long)]
+    pub ignore_stdin: bool,
+
+    /// Do not send the request, only print it.
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Save the response body to a file instead of printing it.
+    #[structopt(short = "d", long)]
+    pub download: bool,
+
+    /// Time limit, in seconds, for the whole request (connect, send, receive).
+    ///
+    /// If the deadline passes before the response finishes downloading, any
+    /// partially-written output file is removed and a timeout error is
+    /// returned instead of a truncated body.
+    #[structopt(long)]
+    pub timeout: Option<f64>,
+
+    /// Follow redirects instead of printing the 3xx response as-is.
+    #[structopt(short = "F", long)]
+    pub follow: bool,
+
+    /// Maximum number of redirects to follow. Implies --follow.
+    #[structopt(long)]
+    pub max_redirects: Option<usize>,
+
+    /// Keep the Authorization header when a redirect sends the request to a
+    /// different host. By default it's dropped, like reqwest/curl do.
+    #[structopt(long)]
+    pub auth_any_host: bool,
+
+    /// Create or reuse a session, storing cookies and auth headers so later
+    /// requests can reuse them. Takes a name (stored under the config dir,
+    /// scoped to the request's host) or a path to a session file.
+    #[structopt(long)]
+    pub session: Option<String>,
+
+    /// Like --session, but never write changes back to the session file.
+    #[structopt(long)]
+    pub session_read_only: Option<String>,
+
+    /// Compress the request body before sending it: `--compress=gzip` (the
+    /// default) or `--compress=deflate`. A bare `--compress`, with no value,
+    /// also means gzip. Bodies under 512 bytes are sent uncompressed
+    /// regardless, since framing overhead would outweigh any savings. Has no
+    /// effect on --multipart bodies.
+    #[structopt(long, possible_values = &["gzip", "deflate"])]
+    pub compress: Option<Compression>,
+
+    /// Use HTTP/1.1, refusing to negotiate HTTP/2 even if the server offers
+    /// it via ALPN.
+    #[structopt(
+        name = "http1.1",
+        long = "http1.1",
+        conflicts_with_all = &["http2", "http2-prior-knowledge"]
+    )]
+    pub http1: bool,
+
+    /// Use HTTP/2, negotiated over ALPN during the TLS handshake. The request
+    /// is sent as HTTP/2, so it fails rather than falling back to HTTP/1.1
+    /// when the connection ends up on HTTP/1.1. Use `--http2-prior-knowledge`
+    /// to skip the negotiation.
+    #[structopt(long = "http2", conflicts_with = "http2-prior-knowledge")]
+    pub http2: bool,
+
+    /// Use HTTP/2 directly over a cleartext connection, skipping protocol
+    /// negotiation entirely.
+    #[structopt(long = "http2-prior-knowledge")]
+    pub http2_prior_knowledge: bool,
+}
+
+impl Opt {
+    /// Like `StructOpt::from_args`, but first defaults a bare `--compress`
+    /// (no `=gzip`/`=deflate`) to gzip. Clap has no way to say "this option
+    /// takes a value, but the value itself is optional": `possible_values`
+    /// on `Option<Compression>` is what makes `--compress=gzip` and
+    /// `--compress gzip` work, but a bare `--compress` would otherwise
+    /// silently swallow whatever comes right after it as the scheme (e.g.
+    /// `xh --compress POST url` trying to parse `POST` as a compression
+    /// scheme). Rewriting the argument list before handing it to clap keeps
+    /// both forms working without giving up the `possible_values` checking.
+    pub fn from_args() -> Opt {
+        StructOpt::from_iter(default_bare_compress(std::env::args_os()))
+    }
+
+    pub fn timeout_duration(&self) -> Option<Duration> {
+        self.timeout.map(Duration::from_secs_f64)
+    }
+
+    pub fn follow_redirects(&self) -> bool {
+        self.follow || self.max_redirects.is_some()
+    }
+
+    pub fn max_redirects(&self) -> usize {
+        self.max_redirects
+            .unwrap_or(crate::redirect::DEFAULT_MAX_REDIRECTS)
+    }
+
+    /// The HTTP version to force on the request, if the user picked one with
+    /// `--http1.1`/`--http2`/`--http2-prior-knowledge`. `None` leaves it to
+    /// reqwest's usual ALPN negotiation.
+    pub fn http_version(&self) -> Option<reqwest::Version> {
+        if self.http2 || self.http2_prior_knowledge {
+            Some(reqwest::Version::HTTP_2)
+        } else if self.http1 {
+            Some(reqwest::Version::HTTP_11)
+        } else {
+            None
+        }
+    }
+
+    /// Resolve `--session`/`--session-read-only` into a session file path
+    /// and whether it should be treated as read-only. `--session-read-only`
+    /// wins if both are somehow given.
+    pub fn session_path(&self, host: &str) -> Option<(std::path::PathBuf, bool)> {
+        if let Some(name_or_path) = &self.session_read_only {
+            Some((resolve_session_path(host, name_or_path), true))
+        } else {
+            self.session
+                .as_ref()
+                .map(|name_or_path| (resolve_session_path(host, name_or_path), false))
+        }
+    }
+}
+
+fn resolve_session_path(host: &str, name_or_path: &str) -> std::path::PathBuf {
+    if name_or_path.contains('/') || name_or_path.ends_with(".json") {
+        std::path::PathBuf::from(name_or_path)
+    } else {
+        crate::session::Session::named_path(host, name_or_path)
+    }
+}
+
+/// Insert a `gzip` argument right after a bare `--compress` (one not spelled
+/// `--compress=...`) that isn't already followed by a valid scheme, so clap
+/// sees an explicit value instead of reaching for whatever token comes next
+/// on the command line.
+fn default_bare_compress(args: impl Iterator<Item = OsString>) -> Vec<OsString> {
+    let args: Vec<OsString> = args.collect();
+    let mut out = Vec::with_capacity(args.len() + 1);
+    let mut iter = args.into_iter().peekable();
+
+    while let Some(arg) = iter.next() {
+        let is_bare_compress = arg == "--compress";
+        out.push(arg);
+        if is_bare_compress {
+            let needs_default = !matches!(
+                iter.peek().and_then(|next| next.to_str()),
+                Some("gzip") | Some("deflate")
+            );
+            if needs_default {
+                out.push(OsString::from("gzip"));
+            }
+        }
+    }
+
+    out
+}
+
+#[derive(Debug)]
+pub struct CliMethod(pub Method);
+
+impl FromStr for CliMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Method::from_str(&s.to_uppercase())
+            .map(CliMethod)
+            .map_err(|_| format!("invalid HTTP method: {}", s))
+    }
+}
+
+impl From<CliMethod> for Method {
+    fn from(method: CliMethod) -> Method {
+        method.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pretty {
+    All,
+    Colors,
+    Format,
+    None,
+}
+
+impl FromStr for Pretty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Pretty::All),
+            "colors" => Ok(Pretty::Colors),
+            "format" => Ok(Pretty::Format),
+            "none" => Ok(Pretty::None),
+            _ => Err(format!("invalid pretty mode: {}", s)),
+        }
+    }
+}
+
+impl Default for Pretty {
+    fn default() -> Self {
+        Pretty::All
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    Auto,
+    Solarized,
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Theme::Auto),
+            "solarized" => Ok(Theme::Solarized),
+            _ => Err(format!("invalid theme: {}", s)),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::Auto
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Deflate,
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gzip" => Ok(Compression::Gzip),
+            "deflate" => Ok(Compression::Deflate),
+            _ => Err(format!("invalid compression scheme: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum AuthType {
+    Basic,
+    Bearer,
+}
+
+impl FromStr for AuthType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "basic" => Ok(AuthType::Basic),
+            "bearer" => Ok(AuthType::Bearer),
+            _ => Err(format!("invalid auth type: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Print {
+    pub request_headers: bool,
+    pub request_body: bool,
+    pub response_headers: bool,
+    pub response_body: bool,
+}
+
+impl Print {
+    pub fn new(
+        request_headers: bool,
+        request_body: bool,
+        response_headers: bool,
+        response_body: bool,
+    ) -> Print {
+        Print {
+            request_headers,
+            request_body,
+            response_headers,
+            response_body,
+        }
+    }
+}
+
+impl FromStr for Print {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Print::new(
+            s.contains('H'),
+            s.contains('B'),
+            s.contains('h'),
+            s.contains('b'),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opt(args: &[&str]) -> Opt {
+        let mut full = vec!["xh", "GET", "http://example.com"];
+        full.extend_from_slice(args);
+        Opt::from_iter(&full)
+    }
+
+    #[test]
+    fn http_version_defaults_to_reqwest_negotiating_on_its_own() {
+        assert_eq!(opt(&[]).http_version(), None);
+    }
+
+    #[test]
+    fn http1_1_forces_http_11() {
+        assert_eq!(opt(&["--http1.1"]).http_version(), Some(reqwest::Version::HTTP_11));
+    }
+
+    #[test]
+    fn http2_forces_http2() {
+        assert_eq!(opt(&["--http2"]).http_version(), Some(reqwest::Version::HTTP_2));
+    }
+
+    #[test]
+    fn http2_prior_knowledge_also_reports_http2() {
+        assert_eq!(
+            opt(&["--http2-prior-knowledge"]).http_version(),
+            Some(reqwest::Version::HTTP_2)
+        );
+    }
+
+    fn rewrite(args: &[&str]) -> Vec<OsString> {
+        default_bare_compress(args.iter().map(OsString::from))
+    }
+
+    fn osstrings(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn bare_compress_defaults_to_gzip() {
+        assert_eq!(
+            rewrite(&["xh", "--compress", "POST", "example.com"]),
+            osstrings(&["xh", "--compress", "gzip", "POST", "example.com"])
+        );
+    }
+
+    #[test]
+    fn bare_compress_at_the_end_of_the_command_line_still_defaults() {
+        assert_eq!(
+            rewrite(&["xh", "POST", "example.com", "--compress"]),
+            osstrings(&["xh", "POST", "example.com", "--compress", "gzip"])
+        );
+    }
+
+    #[test]
+    fn explicit_compress_value_is_left_alone() {
+        assert_eq!(
+            rewrite(&["xh", "--compress", "deflate", "POST", "example.com"]),
+            osstrings(&["xh", "--compress", "deflate", "POST", "example.com"])
+        );
+    }
+
+    #[test]
+    fn compress_equals_syntax_is_untouched_by_the_rewrite() {
+        assert_eq!(
+            rewrite(&["xh", "--compress=gzip", "POST", "example.com"]),
+            osstrings(&["xh", "--compress=gzip", "POST", "example.com"])
+        );
+    }
+
+    #[test]
+    fn compress_equals_syntax_parses_to_the_requested_scheme() {
+        assert_eq!(
+            opt(&["--compress=deflate"]).compress,
+            Some(Compression::Deflate)
+        );
+        assert_eq!(opt(&[]).compress, None);
+    }
+}
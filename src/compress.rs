@@ -0,0 +1,117 @@
+use std::io::Write;
+
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::Compression as Level;
+use reqwest::header::{HeaderValue, CONTENT_ENCODING, CONTENT_TYPE};
+use reqwest::RequestBuilder;
+
+use crate::cli::Compression;
+
+/// Below this size, gzip/deflate framing overhead tends to outweigh any
+/// savings, so we send the body as-is instead.
+const MIN_COMPRESSED_SIZE: usize = 512;
+
+fn compress(body: &[u8], scheme: Compression) -> Option<(Vec<u8>, &'static str)> {
+    if body.len() < MIN_COMPRESSED_SIZE {
+        return None;
+    }
+    let compressed = match scheme {
+        Compression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+            encoder.write_all(body).ok()?;
+            encoder.finish().ok()?
+        }
+        Compression::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Level::default());
+            encoder.write_all(body).ok()?;
+            encoder.finish().ok()?
+        }
+    };
+    let encoding = match scheme {
+        Compression::Gzip => "gzip",
+        Compression::Deflate => "deflate",
+    };
+    Some((compressed, encoding))
+}
+
+/// Attach `body` to `request_builder` under `content_type`, compressing it
+/// with `scheme` first when it's worth the trouble. Setting
+/// `Content-Encoding` only when compression actually happened keeps small
+/// bodies from picking up a misleading header.
+pub fn attach(
+    request_builder: RequestBuilder,
+    body: Vec<u8>,
+    scheme: Compression,
+    content_type: &'static str,
+) -> RequestBuilder {
+    let request_builder =
+        request_builder.header(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    match compress(&body, scheme) {
+        Some((compressed, encoding)) => request_builder
+            .header(CONTENT_ENCODING, HeaderValue::from_static(encoding))
+            .body(compressed),
+        None => request_builder.body(body),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::{DeflateDecoder, GzDecoder};
+    use std::io::Read as _;
+
+    #[test]
+    fn bodies_under_the_threshold_are_left_uncompressed() {
+        let body = vec![b'x'; MIN_COMPRESSED_SIZE - 1];
+        assert!(compress(&body, Compression::Gzip).is_none());
+        assert!(compress(&body, Compression::Deflate).is_none());
+    }
+
+    #[test]
+    fn gzip_scheme_round_trips_and_reports_gzip_encoding() {
+        let body = vec![b'x'; MIN_COMPRESSED_SIZE * 2];
+        let (compressed, encoding) = compress(&body, Compression::Gzip).unwrap();
+        assert_eq!(encoding, "gzip");
+
+        let mut decoded = Vec::new();
+        GzDecoder::new(&compressed[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+    }
+
+    #[test]
+    fn deflate_scheme_round_trips_and_reports_deflate_encoding() {
+        let body = vec![b'x'; MIN_COMPRESSED_SIZE * 2];
+        let (compressed, encoding) = compress(&body, Compression::Deflate).unwrap();
+        assert_eq!(encoding, "deflate");
+
+        let mut decoded = Vec::new();
+        DeflateDecoder::new(&compressed[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+    }
+
+    #[test]
+    fn attach_only_sets_content_encoding_when_compression_actually_happened() {
+        let client = reqwest::Client::new();
+
+        let small = client.post("http://example.com/");
+        let request = attach(small, vec![b'x'; 10], Compression::Gzip, "application/json")
+            .build()
+            .unwrap();
+        assert!(!request.headers().contains_key(CONTENT_ENCODING));
+
+        let large = client.post("http://example.com/");
+        let request = attach(
+            large,
+            vec![b'x'; MIN_COMPRESSED_SIZE * 2],
+            Compression::Gzip,
+            "application/json",
+        )
+        .build()
+        .unwrap();
+        assert_eq!(request.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+    }
+}
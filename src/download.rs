@@ -0,0 +1,18 @@
+use std::io;
+use std::path::Path;
+
+use futures::StreamExt;
+use reqwest::Response;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+pub async fn download_file(response: Response, file_name: &Path) -> io::Result<()> {
+    let mut file = File::create(file_name).await?;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    Ok(())
+}
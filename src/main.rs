@@ -1,7 +1,9 @@
 use std::io::{self, Read};
+use std::path::PathBuf;
+use std::time::Instant;
 
 use atty::Stream;
-use reqwest::header::{HeaderValue, ACCEPT, ACCEPT_ENCODING, CONNECTION, CONTENT_TYPE, HOST};
+use reqwest::header::{HeaderValue, ACCEPT, AUTHORIZATION, CONNECTION, CONTENT_TYPE, HOST};
 use reqwest::Client;
 use structopt::StructOpt;
 #[macro_use]
@@ -9,9 +11,12 @@ extern crate lazy_static;
 
 mod auth;
 mod cli;
+mod compress;
 mod download;
 mod printer;
+mod redirect;
 mod request_items;
+mod session;
 mod url;
 mod utils;
 
@@ -20,7 +25,9 @@ use download::download_file;
 use cli::{AuthType, Opt, Pretty, Print, RequestItem, Theme};
 use printer::Printer;
 use request_items::{Body, RequestItems};
+use std::sync::{Arc, Mutex};
 use url::Url;
+use utils::file_name_from_response;
 
 fn body_from_stdin(ignore_stdin: bool) -> Option<Body> {
     if atty::is(Stream::Stdin) || ignore_stdin {
@@ -44,7 +51,17 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     let method = opt.method.into();
     let auth = Auth::new(opt.auth, opt.auth_type, &url);
     let query = request_items.query();
-    let (headers, headers_to_unset) = request_items.headers();
+    let (mut headers, headers_to_unset) = request_items.headers();
+
+    let mut session = match opt.session_path(&host) {
+        Some((path, read_only)) => {
+            let session = session::Session::load(&path, read_only)?;
+            session.merge_headers(&mut headers);
+            Some(session)
+        }
+        None => None,
+    };
+
     let body = match (
         request_items.body(opt.form, opt.multipart).await?,
         body_from_stdin(opt.ignore_stdin),
@@ -58,35 +75,103 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
         (None, None) => None,
     };
 
-    let client = Client::new();
+    let timeout = opt.timeout_duration();
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    let follow = opt.follow_redirects();
+    let max_redirects = opt.max_redirects();
+    let auth_any_host = opt.auth_any_host;
+    let redirect_chain: redirect::RedirectChain = Arc::new(Mutex::new(Vec::new()));
+
+    let http_version = opt.http_version();
+    let compression = opt.compress;
+
+    let mut client_builder = Client::builder().redirect(redirect::policy(
+        follow && !auth_any_host,
+        max_redirects,
+        redirect_chain.clone(),
+    ));
+    if let Some(session) = &session {
+        client_builder = client_builder.cookie_provider(session.jar.clone());
+    }
+    if opt.http1 {
+        client_builder = client_builder.http1_only();
+    } else if opt.http2_prior_knowledge {
+        client_builder = client_builder.http2_prior_knowledge();
+    }
+    // Let reqwest own Accept-Encoding and transparently decompress the
+    // response itself. A hand-set Accept-Encoding header (the old approach)
+    // disables reqwest's automatic decompression, so the printer would be
+    // handed raw compressed bytes instead of text.
+    let client = client_builder
+        .gzip(true)
+        .deflate(true)
+        .brotli(true)
+        .build()?;
     let request = {
         let mut request_builder = client
             .request(method, url.0)
             .header(ACCEPT, HeaderValue::from_static("*/*"))
-            .header(ACCEPT_ENCODING, HeaderValue::from_static("gzip, deflate"))
             .header(CONNECTION, HeaderValue::from_static("keep-alive"))
             .header(HOST, HeaderValue::from_str(&host).unwrap());
 
-        request_builder = match body {
-            Some(Body::Form(body)) => request_builder.form(&body),
-            Some(Body::Multipart(body)) => request_builder.multipart(body),
-            Some(Body::Json(body)) => request_builder
+        if let Some(timeout) = timeout {
+            request_builder = request_builder.timeout(timeout);
+        }
+        if let Some(version) = http_version {
+            request_builder = request_builder.version(version);
+        }
+
+        request_builder = match (body, compression) {
+            (Some(Body::Form(body)), Some(scheme)) => compress::attach(
+                request_builder,
+                serde_urlencoded::to_string(&body)?.into_bytes(),
+                scheme,
+                "application/x-www-form-urlencoded",
+            ),
+            (Some(Body::Form(body)), None) => request_builder.form(&body),
+            (Some(Body::Multipart(body)), _) => request_builder.multipart(body),
+            (Some(Body::Json(body)), Some(scheme)) => compress::attach(
+                request_builder
+                    .header(ACCEPT, HeaderValue::from_static("application/json, */*")),
+                serde_json::to_vec(&body)?,
+                scheme,
+                "application/json",
+            ),
+            (Some(Body::Json(body)), None) => request_builder
                 .header(ACCEPT, HeaderValue::from_static("application/json, */*"))
                 .json(&body),
-            Some(Body::Raw(body)) => request_builder
+            (Some(Body::Raw(body)), Some(scheme)) => compress::attach(
+                request_builder
+                    .header(ACCEPT, HeaderValue::from_static("application/json, */*")),
+                body.into_bytes(),
+                scheme,
+                "application/json",
+            ),
+            (Some(Body::Raw(body)), None) => request_builder
                 .header(ACCEPT, HeaderValue::from_static("application/json, */*"))
                 .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
                 .body(body),
-            None => request_builder,
+            (None, _) => request_builder,
         };
 
+        // Apply session/-H headers before `auth`: `RequestBuilder::headers`
+        // replaces same-named headers already on the builder, so applying
+        // them afterwards would clobber a fresh `--auth`. `bearer_auth`/
+        // `basic_auth` append rather than replace, so a stored session
+        // `Authorization` is dropped first when `--auth` is given.
+        if auth.is_some() {
+            headers.remove(AUTHORIZATION);
+        }
+        request_builder = request_builder.headers(headers);
+
         request_builder = match auth {
             Some(Auth::Bearer(token)) => request_builder.bearer_auth(token),
             Some(Auth::Basic(username, password)) => request_builder.basic_auth(username, password),
             None => request_builder,
         };
 
-        let mut request = request_builder.query(&query).headers(headers).build()?;
+        let mut request = request_builder.query(&query).build()?;
 
         headers_to_unset.iter().for_each(|h| {
             request.headers_mut().remove(h);
@@ -110,15 +195,157 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
         printer.print_request_body(&request);
     }
     if !opt.offline {
-        let response = client.execute(request).await?;
+        let sent_headers = request.headers().clone();
+        let response = if follow && auth_any_host {
+            redirect::execute_preserving_auth(
+                &client,
+                request,
+                max_redirects,
+                &redirect_chain,
+                deadline,
+            )
+            .await?
+        } else {
+            client.execute(request).await?
+        };
+        if let Some(session) = &mut session {
+            session.record_auth_header(&sent_headers);
+            session.save()?;
+        }
+        if opt.verbose {
+            printer.print_redirects(&redirect_chain.lock().unwrap());
+        }
         if print.response_headers {
             printer.print_response_headers(&response);
         }
         if opt.download {
-            download_file(response).await;
+            let file_name = PathBuf::from(file_name_from_response(&response));
+            match remaining(deadline) {
+                Some(remaining) => {
+                    match tokio::time::timeout(remaining, download_file(response, &file_name)).await
+                    {
+                        Ok(result) => result?,
+                        Err(_) => {
+                            let _ = std::fs::remove_file(&file_name);
+                            return Err(
+                                "request timed out while downloading the response body".into()
+                            );
+                        }
+                    }
+                }
+                None => download_file(response, &file_name).await?,
+            }
         } else if print.response_body {
-            printer.print_response_body(response).await;
+            match remaining(deadline) {
+                Some(remaining) => {
+                    if tokio::time::timeout(remaining, printer.print_response_body(response))
+                        .await
+                        .is_err()
+                    {
+                        return Err("request timed out while reading the response body".into());
+                    }
+                }
+                None => printer.print_response_body(response).await,
+            }
         }
     }
     Ok(())
 }
+
+/// Time left until `deadline`, or `None` if no deadline was set. A deadline
+/// that has already passed resolves to a zero duration so the next
+/// `tokio::time::timeout` fires immediately instead of waiting forever.
+fn remaining(deadline: Option<Instant>) -> Option<std::time::Duration> {
+    deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::AUTHORIZATION;
+    use std::io::{Read as _, Write as _};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    /// Reproduces the exact builder calls `main` makes: session/-H headers,
+    /// minus `Authorization` since `--auth` is given, via `.headers()`, then
+    /// the per-invocation `auth`. A stale `Authorization` carried over from a
+    /// session file must lose to a fresh `--auth` passed on this invocation,
+    /// not the other way round.
+    #[test]
+    fn fresh_auth_wins_over_stale_session_authorization_header() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_static("Bearer stale-session-token"),
+        );
+        headers.remove(AUTHORIZATION);
+
+        let request = Client::new()
+            .get("http://example.com")
+            .headers(headers)
+            .bearer_auth("fresh-token")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.headers().get(AUTHORIZATION).unwrap(),
+            "Bearer fresh-token"
+        );
+    }
+
+    #[test]
+    fn remaining_saturates_to_zero_once_the_deadline_has_passed() {
+        let deadline = Instant::now() - Duration::from_secs(1);
+        assert_eq!(remaining(Some(deadline)), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn remaining_is_none_without_a_deadline() {
+        assert_eq!(remaining(None), None);
+    }
+
+    /// Reproduces the exact download-timeout-then-cleanup pattern `main`
+    /// uses: a `tokio::time::timeout` wrapping `download_file`, with the
+    /// partially-written output file removed once the deadline hits instead
+    /// of being left on disk as a truncated file.
+    #[tokio::test]
+    async fn download_timeout_removes_the_partial_file() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf);
+            // Promise far more body than is ever sent, then stall, so the
+            // client is still waiting on more bytes when its deadline hits.
+            let header = "HTTP/1.1 200 OK\r\nContent-Length: 1000000\r\nConnection: close\r\n\r\n";
+            stream.write_all(header.as_bytes()).unwrap();
+            stream.write_all(b"partial body").unwrap();
+            std::thread::sleep(Duration::from_secs(5));
+        });
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/", addr))
+            .send()
+            .await
+            .unwrap();
+
+        let dir = std::env::temp_dir().join("xh-download-timeout-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file_name = dir.join("partial-download.bin");
+        let _ = std::fs::remove_file(&file_name);
+
+        match tokio::time::timeout(Duration::from_millis(100), download_file(response, &file_name))
+            .await
+        {
+            Ok(result) => panic!("expected the download to time out, got {:?}", result),
+            Err(_) => {
+                let _ = std::fs::remove_file(&file_name);
+            }
+        }
+
+        assert!(!file_name.exists());
+    }
+}
@@ -0,0 +1,188 @@
+use std::io::{self, Write};
+
+use encoding_rs::UTF_8;
+use reqwest::header::{CONTENT_ENCODING, CONTENT_TYPE};
+use reqwest::{Request, Response};
+
+use crate::cli::{Pretty, Theme};
+use crate::redirect::RedirectHop;
+use crate::utils::{charset_from_content_type, format_version, is_text_content_type};
+
+pub struct Printer {
+    pretty: Pretty,
+    #[allow(dead_code)]
+    theme: Theme,
+}
+
+impl Printer {
+    pub fn new(pretty: Pretty, theme: Theme) -> Printer {
+        Printer { pretty, theme }
+    }
+
+    pub fn print_request_headers(&self, request: &Request) {
+        println!("{} {}", request.method(), request.url());
+        for (key, value) in request.headers() {
+            println!("{}: {}", key, value.to_str().unwrap_or(""));
+        }
+        println!();
+    }
+
+    pub fn print_request_body(&self, request: &Request) {
+        if let Some(body) = request.body().and_then(|b| b.as_bytes()) {
+            // `--compress` may have left `body` gzip/deflate-compressed, and
+            // non-text content types were never text to begin with; either
+            // way, lossily decoding it as UTF-8 would just dump control
+            // bytes. Mirror the response side's `DecodedBody::Binary`
+            // handling and write those out raw.
+            if request_body_is_binary(request) {
+                let _ = io::stdout().write_all(body);
+                println!();
+            } else {
+                println!("{}", String::from_utf8_lossy(body));
+                println!();
+            }
+        }
+    }
+
+    pub fn print_redirects(&self, chain: &[RedirectHop]) {
+        for hop in chain {
+            println!("{}", hop.status);
+            if let Some(location) = &hop.location {
+                println!("Location: {}", location);
+            }
+            println!();
+        }
+    }
+
+    pub fn print_response_headers(&self, response: &Response) {
+        println!("{} {}", format_version(response.version()), response.status());
+        for (key, value) in response.headers() {
+            println!("{}: {}", key, value.to_str().unwrap_or(""));
+        }
+        println!();
+    }
+
+    pub async fn print_response_body(&self, response: Response) {
+        match decode_body(response).await {
+            Ok(DecodedBody::Text(text)) => {
+                if self.pretty == Pretty::None {
+                    print!("{}", text);
+                } else {
+                    println!("{}", text);
+                }
+            }
+            Ok(DecodedBody::Binary(bytes)) => {
+                let _ = io::stdout().write_all(&bytes);
+            }
+            Err(err) => eprintln!("error reading response body: {}", err),
+        }
+    }
+}
+
+/// A response body read to completion: text that's been charset-decoded to
+/// UTF-8 and is ready to print, or raw bytes for binary content types that
+/// must be written out untouched. `response.bytes()` is assumed to already
+/// be decompressed (the `Client` is configured with `gzip`/`deflate`/`brotli`
+/// in `main`), so this only has to worry about the text charset.
+enum DecodedBody {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+/// Whether a request body should be written out raw instead of through
+/// `String::from_utf8_lossy`: either `--compress` actually compressed it
+/// (`Content-Encoding` is set) or its `Content-Type` isn't text to begin
+/// with.
+fn request_body_is_binary(request: &Request) -> bool {
+    if request.headers().contains_key(CONTENT_ENCODING) {
+        return true;
+    }
+    request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(false, |content_type| !is_text_content_type(content_type))
+}
+
+async fn decode_body(response: Response) -> reqwest::Result<DecodedBody> {
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let bytes = response.bytes().await?;
+
+    // Binary content types (images, archives, ...) are written out
+    // as-is: transcoding them through a text charset would corrupt them.
+    if !content_type.as_deref().map_or(true, is_text_content_type) {
+        return Ok(DecodedBody::Binary(bytes.to_vec()));
+    }
+
+    let encoding = content_type
+        .as_deref()
+        .and_then(charset_from_content_type)
+        .unwrap_or(UTF_8);
+    let (text, _, _) = encoding.decode(&bytes);
+
+    Ok(DecodedBody::Text(text.into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read as _, Write as _};
+    use std::net::TcpListener;
+
+    /// Serves a single gzip-compressed response over a local socket and
+    /// checks that, once the `Client` is configured to decompress
+    /// automatically (as `main` does), the body we hand to the printer is
+    /// the original text rather than mojibake from charset-decoding
+    /// still-compressed bytes.
+    #[tokio::test]
+    async fn gzip_compressed_body_decodes_to_expected_text() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression as GzLevel;
+
+        let body = "héllo wörld, \u{1F980}\n".repeat(20);
+        let mut encoder = GzEncoder::new(Vec::new(), GzLevel::default());
+        encoder.write_all(body.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = compressed.clone();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf);
+            let header = format!(
+                "HTTP/1.1 200 OK\r\n\
+                 Content-Type: text/plain; charset=utf-8\r\n\
+                 Content-Encoding: gzip\r\n\
+                 Content-Length: {}\r\n\
+                 Connection: close\r\n\r\n",
+                served.len()
+            );
+            stream.write_all(header.as_bytes()).unwrap();
+            stream.write_all(&served).unwrap();
+        });
+
+        let client = reqwest::Client::builder()
+            .gzip(true)
+            .deflate(true)
+            .brotli(true)
+            .build()
+            .unwrap();
+        let response = client
+            .get(format!("http://{}/", addr))
+            .send()
+            .await
+            .unwrap();
+
+        match decode_body(response).await.unwrap() {
+            DecodedBody::Text(text) => assert_eq!(text, body),
+            DecodedBody::Binary(_) => panic!("expected a text body"),
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use reqwest::header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, LOCATION};
+use reqwest::redirect::{Attempt, Policy};
+use reqwest::StatusCode;
+
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// One hop of a followed redirect chain, kept around so `-v` can print the
+/// status line and `Location` of every intermediate response, not just the
+/// final one.
+#[derive(Debug, Clone)]
+pub struct RedirectHop {
+    pub status: StatusCode,
+    pub location: Option<String>,
+}
+
+pub type RedirectChain = Arc<Mutex<Vec<RedirectHop>>>;
+
+/// Build the client's redirect policy.
+///
+/// When `follow` is `false` xh never follows redirects, so the 3xx response
+/// itself is printed as-is (the default, matching curl). When `follow` is
+/// `true`, every attempt is recorded into `chain` before it is allowed or
+/// rejected, and following stops once `max_redirects` hops have been taken.
+/// `reqwest` strips `Authorization`/cookies on cross-host redirects on its
+/// own; `--auth-any-host` is handled by the caller re-attaching the header
+/// for a manual hop when that stripping isn't wanted.
+pub fn policy(follow: bool, max_redirects: usize, chain: RedirectChain) -> Policy {
+    if !follow {
+        return Policy::none();
+    }
+    Policy::custom(move |attempt: Attempt| {
+        chain.lock().unwrap().push(RedirectHop {
+            status: attempt.status(),
+            location: Some(attempt.url().to_string()),
+        });
+        // `previous()` includes the original URL, so it holds one more entry
+        // than the number of hops taken so far.
+        if attempt.previous().len() > max_redirects {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Manually follow redirects one hop at a time, preserving `Authorization`
+/// across host changes. Used only for `--follow --auth-any-host`, since
+/// `reqwest`'s own redirect handling strips sensitive headers cross-host
+/// unconditionally and gives us no hook to opt back in.
+///
+/// `deadline`, if set, is the same overall deadline `--timeout` computed in
+/// `main`: it's re-applied to the request before every hop so the total
+/// wall-clock budget across the whole chain stays bounded, rather than
+/// resetting to the original per-request timeout on each redirect.
+pub async fn execute_preserving_auth(
+    client: &reqwest::Client,
+    mut request: reqwest::Request,
+    max_redirects: usize,
+    chain: &RedirectChain,
+    deadline: Option<Instant>,
+) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+    for _ in 0..=max_redirects {
+        if let Some(deadline) = deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err("request timed out while following redirects".into());
+            }
+            *request.timeout_mut() = Some(remaining);
+        }
+
+        let to_send = request
+            .try_clone()
+            .ok_or("can't follow a redirect for a request body that can't be cloned (e.g. --multipart)")?;
+        let response = client.execute(to_send).await?;
+
+        if !response.status().is_redirection() {
+            return Ok(response);
+        }
+
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| response.url().join(value).ok());
+
+        chain.lock().unwrap().push(RedirectHop {
+            status: response.status(),
+            location: location.as_ref().map(|url| url.to_string()),
+        });
+
+        let next_url = match location {
+            Some(url) => url,
+            None => return Ok(response),
+        };
+
+        if downgrades_to_get(response.status(), request.method()) {
+            *request.method_mut() = reqwest::Method::GET;
+            clear_body(&mut request);
+        }
+        *request.url_mut() = next_url;
+    }
+
+    Err("too many redirects".into())
+}
+
+/// Whether a redirect response downgrades the next hop to a bodyless GET.
+/// Mirrors reqwest's own built-in redirect policy: 303 always downgrades
+/// (unless the request already is GET/HEAD), while 301/302 only downgrade a
+/// POST, matching what browsers and curl -L do.
+fn downgrades_to_get(status: StatusCode, method: &reqwest::Method) -> bool {
+    match status {
+        StatusCode::SEE_OTHER => {
+            *method != reqwest::Method::GET && *method != reqwest::Method::HEAD
+        }
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => *method == reqwest::Method::POST,
+        _ => false,
+    }
+}
+
+/// Drop a request's body along with the headers that described it.
+/// `reqwest`'s own built-in redirect policy clears `Content-Type`/
+/// `Content-Encoding`/`Content-Length` together with the body on a 301/302/303
+/// downgrade to GET; this manual path needs to match that so a downgraded
+/// bodyless request doesn't keep describing a body (and, since `--compress`,
+/// a `Content-Encoding` the server would try to decode) it no longer has.
+fn clear_body(request: &mut reqwest::Request) {
+    *request.body_mut() = None;
+    request.headers_mut().remove(CONTENT_TYPE);
+    request.headers_mut().remove(CONTENT_ENCODING);
+    request.headers_mut().remove(CONTENT_LENGTH);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read as _, Write as _};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use reqwest::Method;
+
+    #[test]
+    fn downgrade_rules_match_303_always_301_302_only_post() {
+        assert!(downgrades_to_get(StatusCode::SEE_OTHER, &Method::POST));
+        assert!(downgrades_to_get(StatusCode::SEE_OTHER, &Method::PUT));
+        assert!(!downgrades_to_get(StatusCode::SEE_OTHER, &Method::GET));
+        assert!(!downgrades_to_get(StatusCode::SEE_OTHER, &Method::HEAD));
+
+        assert!(downgrades_to_get(StatusCode::MOVED_PERMANENTLY, &Method::POST));
+        assert!(!downgrades_to_get(StatusCode::MOVED_PERMANENTLY, &Method::PUT));
+        assert!(downgrades_to_get(StatusCode::FOUND, &Method::POST));
+        assert!(!downgrades_to_get(StatusCode::FOUND, &Method::GET));
+
+        assert!(!downgrades_to_get(StatusCode::TEMPORARY_REDIRECT, &Method::POST));
+    }
+
+    #[test]
+    fn clear_body_drops_body_and_its_describing_headers() {
+        let client = reqwest::Client::new();
+        let mut request = client
+            .post("http://example.com/")
+            .header(CONTENT_TYPE, "application/json")
+            .header(CONTENT_ENCODING, "gzip")
+            .body("{}")
+            .build()
+            .unwrap();
+
+        clear_body(&mut request);
+
+        assert!(request.body().is_none());
+        assert!(!request.headers().contains_key(CONTENT_TYPE));
+        assert!(!request.headers().contains_key(CONTENT_ENCODING));
+        assert!(!request.headers().contains_key(CONTENT_LENGTH));
+    }
+
+    /// A 303 to a POST downgrades the next hop to a bodyless GET, stripping
+    /// `Content-Type` along the way, and the hop is recorded in `chain`.
+    #[tokio::test]
+    async fn execute_preserving_auth_downgrades_303_post_and_records_chain() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf);
+            let resp = format!(
+                "HTTP/1.1 303 See Other\r\nLocation: http://{}/landing\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                addr
+            );
+            stream.write_all(resp.as_bytes()).unwrap();
+
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 2048];
+            let n = stream.read(&mut buf).unwrap();
+            let _ = tx.send(String::from_utf8_lossy(&buf[..n]).to_string());
+            let resp = "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+            stream.write_all(resp.as_bytes()).unwrap();
+        });
+
+        let client = reqwest::Client::builder()
+            .redirect(Policy::none())
+            .build()
+            .unwrap();
+        let request = client
+            .post(format!("http://{}/submit", addr))
+            .header(CONTENT_TYPE, "application/json")
+            .body("{}")
+            .build()
+            .unwrap();
+        let chain: RedirectChain = Arc::new(Mutex::new(Vec::new()));
+
+        let response = execute_preserving_auth(&client, request, 5, &chain, None)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(chain.lock().unwrap().len(), 1);
+
+        let second_hop_request = rx.recv().unwrap();
+        assert!(second_hop_request.starts_with("GET /landing"));
+        assert!(!second_hop_request.to_lowercase().contains("content-type"));
+    }
+
+    /// Once `max_redirects` hops have all come back as redirects, following
+    /// stops and an error is returned instead of looping forever.
+    #[tokio::test]
+    async fn execute_preserving_auth_gives_up_after_max_redirects() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        std::thread::spawn(move || {
+            for _ in 0..3 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf);
+                let resp = format!(
+                    "HTTP/1.1 302 Found\r\nLocation: http://{}/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    addr
+                );
+                let _ = stream.write_all(resp.as_bytes());
+            }
+        });
+
+        let client = reqwest::Client::builder()
+            .redirect(Policy::none())
+            .build()
+            .unwrap();
+        let request = client.get(format!("http://{}/", addr)).build().unwrap();
+        let chain: RedirectChain = Arc::new(Mutex::new(Vec::new()));
+
+        let result = execute_preserving_auth(&client, request, 2, &chain, None).await;
+        assert!(result.is_err());
+        assert_eq!(chain.lock().unwrap().len(), 3);
+    }
+}
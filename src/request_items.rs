@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::io;
+use std::str::FromStr;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::multipart;
+use serde_json::Value;
+
+#[derive(Debug, Clone)]
+pub enum RequestItem {
+    HttpHeader(String, String),
+    HttpHeaderToUnset(String),
+    UrlParam(String, String),
+    DataField(String, String),
+    JsonField(String, Value),
+    FormFile(String, String),
+}
+
+impl FromStr for RequestItem {
+    type Err = String;
+
+    /// Parse HTTPie-style key-value pairs: `key==value` for query params,
+    /// `key:=value` for raw JSON fields, `key@file` for form files,
+    /// `key:value`/`key:` for headers (the latter unsets it), and plain
+    /// `key=value` for data fields.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let separators = [("==", 2), (":=", 2), ("@", 1), (":", 1), ("=", 1)];
+        let mut found: Option<(usize, &str, usize)> = None;
+        for (sep, len) in separators {
+            if let Some(pos) = s.find(sep) {
+                if found.map_or(true, |(p, _, _)| pos < p) {
+                    found = Some((pos, sep, len));
+                }
+            }
+        }
+        let (pos, sep, len) = found.ok_or_else(|| format!("invalid request item: {}", s))?;
+        let key = s[..pos].to_string();
+        let value = s[pos + len..].to_string();
+        match sep {
+            "==" => Ok(RequestItem::UrlParam(key, value)),
+            ":=" => {
+                let value = serde_json::from_str(&value)
+                    .map_err(|err| format!("invalid JSON value for {}: {}", key, err))?;
+                Ok(RequestItem::JsonField(key, value))
+            }
+            "@" => Ok(RequestItem::FormFile(key, value)),
+            ":" if value.is_empty() => Ok(RequestItem::HttpHeaderToUnset(key)),
+            ":" => Ok(RequestItem::HttpHeader(key, value)),
+            "=" => Ok(RequestItem::DataField(key, value)),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Body {
+    Form(HashMap<String, String>),
+    Multipart(multipart::Form),
+    Json(serde_json::Map<String, Value>),
+    Raw(String),
+}
+
+pub struct RequestItems(Vec<RequestItem>);
+
+impl RequestItems {
+    pub fn new(request_items: Vec<RequestItem>) -> RequestItems {
+        RequestItems(request_items)
+    }
+
+    pub fn headers(&self) -> (HeaderMap<HeaderValue>, Vec<HeaderName>) {
+        let mut headers = HeaderMap::new();
+        let mut headers_to_unset = Vec::new();
+        for item in &self.0 {
+            match item {
+                RequestItem::HttpHeader(key, value) => {
+                    let key = HeaderName::from_bytes(key.as_bytes()).unwrap();
+                    let value = HeaderValue::from_str(value).unwrap();
+                    headers.insert(key, value);
+                }
+                RequestItem::HttpHeaderToUnset(key) => {
+                    let key = HeaderName::from_bytes(key.as_bytes()).unwrap();
+                    headers_to_unset.push(key);
+                }
+                _ => {}
+            }
+        }
+        (headers, headers_to_unset)
+    }
+
+    pub fn query(&self) -> Vec<(&String, &String)> {
+        self.0
+            .iter()
+            .filter_map(|item| match item {
+                RequestItem::UrlParam(key, value) => Some((key, value)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub async fn body(
+        &self,
+        as_form: bool,
+        as_multipart: bool,
+    ) -> io::Result<Option<Body>> {
+        if as_multipart {
+            let mut form = multipart::Form::new();
+            for item in &self.0 {
+                match item {
+                    RequestItem::DataField(key, value) => {
+                        form = form.text(key.clone(), value.clone());
+                    }
+                    RequestItem::FormFile(key, path) => {
+                        form = form.file(key.clone(), path).await?;
+                    }
+                    _ => {}
+                }
+            }
+            return Ok(Some(Body::Multipart(form)));
+        }
+
+        let mut fields = Vec::new();
+        for item in &self.0 {
+            match item {
+                RequestItem::DataField(key, value) => fields.push((key, value)),
+                RequestItem::JsonField(..) => {}
+                _ => {}
+            }
+        }
+
+        if as_form {
+            if fields.is_empty() {
+                return Ok(None);
+            }
+            let form = fields
+                .into_iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+            Ok(Some(Body::Form(form)))
+        } else {
+            let mut map = serde_json::Map::new();
+            let mut has_json = false;
+            for item in &self.0 {
+                match item {
+                    RequestItem::DataField(key, value) => {
+                        map.insert(key.clone(), Value::String(value.clone()));
+                        has_json = true;
+                    }
+                    RequestItem::JsonField(key, value) => {
+                        map.insert(key.clone(), value.clone());
+                        has_json = true;
+                    }
+                    _ => {}
+                }
+            }
+            if has_json {
+                Ok(Some(Body::Json(map)))
+            } else {
+                Ok(None)
+            }
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use cookie_store::{Cookie as RawCookie, CookieStore as RawCookieStore};
+use reqwest::cookie::CookieStore;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+/// A cookie jar that can be installed on a `reqwest::Client` via
+/// `cookie_provider` and also flattened to/from the session file. We keep
+/// the raw `Set-Cookie` strings around in `seen` alongside the real
+/// `cookie_store::CookieStore` (used to compute the `Cookie` header) since
+/// the store itself doesn't expose enough to round-trip through JSON.
+#[derive(Default)]
+pub struct Jar {
+    store: RwLock<RawCookieStore>,
+    seen: Mutex<Vec<StoredCookie>>,
+}
+
+impl CookieStore for Jar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let mut store = self.store.write().unwrap();
+        let mut seen = self.seen.lock().unwrap();
+        for header in cookie_headers {
+            let raw = match header.to_str() {
+                Ok(raw) => raw,
+                Err(_) => continue,
+            };
+            if let Ok(cookie) = RawCookie::parse(raw.to_owned(), url) {
+                let _ = store.insert_raw(&cookie, url);
+                let identity = cookie_identity(raw, url);
+                seen.retain(|stored| stored.identity() != identity);
+                seen.push(StoredCookie {
+                    url: url.to_string(),
+                    raw: raw.to_string(),
+                });
+            }
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let store = self.store.read().unwrap();
+        let value = store
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if value.is_empty() {
+            None
+        } else {
+            HeaderValue::from_str(&value).ok()
+        }
+    }
+}
+
+impl Jar {
+    fn replay(&self, cookies: &[StoredCookie]) {
+        for stored in cookies {
+            if let Ok(url) = Url::parse(&stored.url) {
+                if let Ok(header) = HeaderValue::from_str(&stored.raw) {
+                    self.set_cookies(&mut std::iter::once(&header), &url);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredCookie {
+    url: String,
+    raw: String,
+}
+
+impl StoredCookie {
+    /// The (name, domain, path) this cookie was last stored under, used to
+    /// replace a re-issued cookie in place instead of growing `seen`
+    /// unbounded every time a server rotates a session ID or CSRF token.
+    fn identity(&self) -> (String, String, String) {
+        match Url::parse(&self.url) {
+            Ok(url) => cookie_identity(&self.raw, &url),
+            Err(_) => (self.raw.clone(), String::new(), String::new()),
+        }
+    }
+}
+
+/// Extract a cookie's identity from its raw `Set-Cookie` value: the name
+/// (before the first `=`) plus its `Domain`/`Path` attributes, falling back
+/// to the request URL's host and `/` when those attributes are absent.
+fn cookie_identity(raw: &str, url: &Url) -> (String, String, String) {
+    let mut name = String::new();
+    let mut domain = url.host_str().unwrap_or_default().to_string();
+    let mut path = "/".to_string();
+
+    for (index, part) in raw.split(';').enumerate() {
+        let part = part.trim();
+        if index == 0 {
+            name = part.split('=').next().unwrap_or("").to_string();
+        } else if let Some(value) = part
+            .strip_prefix("Domain=")
+            .or_else(|| part.strip_prefix("domain="))
+        {
+            domain = value.trim_start_matches('.').to_string();
+        } else if let Some(value) = part
+            .strip_prefix("Path=")
+            .or_else(|| part.strip_prefix("path="))
+        {
+            path = value.to_string();
+        }
+    }
+
+    (name, domain, path)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SessionFile {
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    cookies: Vec<StoredCookie>,
+}
+
+/// An HTTPie-style session: a cookie jar plus a set of default headers,
+/// persisted as JSON so a login request run once can be reused by later
+/// invocations.
+pub struct Session {
+    path: PathBuf,
+    read_only: bool,
+    pub jar: std::sync::Arc<Jar>,
+    pub headers: HeaderMap,
+}
+
+impl Session {
+    pub fn load(path: impl AsRef<Path>, read_only: bool) -> io::Result<Session> {
+        let path = path.as_ref().to_path_buf();
+        let jar = std::sync::Arc::new(Jar::default());
+        let mut headers = HeaderMap::new();
+
+        if let Ok(contents) = fs::read_to_string(&path) {
+            let file: SessionFile = serde_json::from_str(&contents)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            for (key, value) in &file.headers {
+                if let (Ok(name), Ok(value)) = (
+                    HeaderName::from_bytes(key.as_bytes()),
+                    HeaderValue::from_str(value),
+                ) {
+                    headers.insert(name, value);
+                }
+            }
+            jar.replay(&file.cookies);
+        }
+
+        Ok(Session {
+            path,
+            read_only,
+            jar,
+            headers,
+        })
+    }
+
+    /// Where xh keeps a named session, mirroring HTTPie's layout:
+    /// `~/.config/xh/sessions/<host>/<name>.json`.
+    pub fn named_path(host: &str, name: &str) -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("xh")
+            .join("sessions")
+            .join(host)
+            .join(format!("{}.json", name))
+    }
+
+    /// Merge the session's stored headers under this invocation's headers,
+    /// so a `-H` passed on the command line always wins.
+    pub fn merge_headers(&self, headers: &mut HeaderMap) {
+        for (key, value) in &self.headers {
+            if !headers.contains_key(key) {
+                headers.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    /// Remember the auth header used for this request so it's sent
+    /// automatically next time, without needing `--auth` again. `headers` is
+    /// the request's final, post-`headers_to_unset` header set, so a request
+    /// that ends up with no `Authorization` (e.g. `-H "Authorization:"`)
+    /// clears the stored value instead of leaving a stale credential that
+    /// would otherwise keep being resent forever.
+    pub fn record_auth_header(&mut self, headers: &HeaderMap) {
+        match headers.get(AUTHORIZATION) {
+            Some(value) => {
+                self.headers.insert(AUTHORIZATION, value.clone());
+            }
+            None => {
+                self.headers.remove(AUTHORIZATION);
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let headers = self
+            .headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or_default().to_string()))
+            .collect();
+        let cookies = self.jar.seen.lock().unwrap().clone();
+
+        let file = SessionFile { headers, cookies };
+        let contents = serde_json::to_string_pretty(&file)?;
+        fs::write(&self.path, contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cookies_round_trip_through_the_session_file() {
+        let dir = std::env::temp_dir().join("xh-session-test-cookies");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.json");
+        let _ = fs::remove_file(&path);
+
+        let url = Url::parse("https://example.com/").unwrap();
+        let session = Session::load(&path, false).unwrap();
+        let header = HeaderValue::from_static("token=abc123; Path=/");
+        session.jar.set_cookies(&mut std::iter::once(&header), &url);
+        session.save().unwrap();
+
+        let reloaded = Session::load(&path, false).unwrap();
+        let sent = reloaded.jar.cookies(&url).unwrap();
+        assert_eq!(sent.to_str().unwrap(), "token=abc123");
+
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn re_issued_cookie_replaces_its_old_entry_instead_of_duplicating() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let jar = Jar::default();
+
+        let first = HeaderValue::from_static("session_id=aaa; Path=/");
+        jar.set_cookies(&mut std::iter::once(&first), &url);
+        let second = HeaderValue::from_static("session_id=bbb; Path=/");
+        jar.set_cookies(&mut std::iter::once(&second), &url);
+
+        let seen = jar.seen.lock().unwrap();
+        assert_eq!(seen.len(), 1, "re-issuing the same cookie should replace it, not grow seen: {:?}", *seen);
+        assert!(seen[0].raw.contains("bbb"));
+    }
+
+    #[test]
+    fn command_line_headers_win_over_session_headers() {
+        let mut session_headers = HeaderMap::new();
+        session_headers.insert(
+            HeaderName::from_static("x-api-key"),
+            HeaderValue::from_static("from-session"),
+        );
+        let session = Session {
+            path: PathBuf::from("/tmp/unused-session.json"),
+            read_only: true,
+            jar: std::sync::Arc::new(Jar::default()),
+            headers: session_headers,
+        };
+
+        let mut request_headers = HeaderMap::new();
+        request_headers.insert(
+            HeaderName::from_static("x-api-key"),
+            HeaderValue::from_static("from-command-line"),
+        );
+        session.merge_headers(&mut request_headers);
+
+        assert_eq!(request_headers["x-api-key"], "from-command-line");
+    }
+
+    #[test]
+    fn record_auth_header_forgets_a_stored_token_once_unset() {
+        let mut session_headers = HeaderMap::new();
+        session_headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer stale-token"));
+        let mut session = Session {
+            path: PathBuf::from("/tmp/unused-session.json"),
+            read_only: true,
+            jar: std::sync::Arc::new(Jar::default()),
+            headers: session_headers,
+        };
+
+        // The request that actually went out had no Authorization header
+        // (e.g. -H "Authorization:" unset it), so the stored token should be
+        // forgotten rather than kept around forever.
+        session.record_auth_header(&HeaderMap::new());
+
+        assert!(!session.headers.contains_key(AUTHORIZATION));
+    }
+}
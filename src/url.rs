@@ -0,0 +1,22 @@
+pub struct Url(pub reqwest::Url);
+
+impl Url {
+    pub fn new(url: String, default_scheme: Option<String>) -> Url {
+        let default_scheme = default_scheme.unwrap_or_else(|| "http".to_string());
+        let url = if url.contains("://") {
+            url
+        } else if let Some(rest) = url.strip_prefix(':') {
+            format!("{}://localhost{}", default_scheme, rest)
+        } else {
+            format!("{}://{}", default_scheme, url)
+        };
+        Url(reqwest::Url::parse(&url).unwrap())
+    }
+
+    pub fn host(&self) -> Option<String> {
+        self.0.host_str().map(|host| match self.0.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        })
+    }
+}
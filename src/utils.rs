@@ -0,0 +1,68 @@
+use encoding_rs::Encoding;
+use reqwest::{Response, Version};
+
+/// Guess a reasonable file name to save a downloaded response under, falling
+/// back to a generic name when the response gives us nothing better.
+pub fn file_name_from_response(response: &Response) -> String {
+    response
+        .url()
+        .path_segments()
+        .and_then(|segments| segments.last())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("index.html")
+        .to_string()
+}
+
+/// Pull the charset out of a `Content-Type` header value, e.g.
+/// `text/html; charset=iso-8859-1` -> `windows-1252`, the encoding
+/// `encoding_rs` resolves that label to. Returns
+/// `None` when absent or unrecognised, in which case callers should assume
+/// UTF-8.
+pub fn charset_from_content_type(content_type: &str) -> Option<&'static Encoding> {
+    content_type
+        .split(';')
+        .skip(1)
+        .find_map(|param| param.trim().strip_prefix("charset="))
+        .and_then(|charset| Encoding::for_label(charset.trim_matches('"').as_bytes()))
+}
+
+/// Whether a `Content-Type` looks like text worth charset-decoding and
+/// pretty-printing, as opposed to binary content that should be written out
+/// untouched.
+pub fn is_text_content_type(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence,
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/x-www-form-urlencoded"
+        )
+}
+
+/// Render a negotiated protocol version the way curl/httpie do on the status
+/// line, e.g. `HTTP/2` rather than `HTTP/2.0`.
+pub fn format_version(version: Version) -> &'static str {
+    match version {
+        Version::HTTP_09 => "HTTP/0.9",
+        Version::HTTP_10 => "HTTP/1.0",
+        Version::HTTP_11 => "HTTP/1.1",
+        Version::HTTP_2 => "HTTP/2",
+        Version::HTTP_3 => "HTTP/3",
+        _ => "HTTP/unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_version_reports_the_negotiated_protocol_like_curl() {
+        assert_eq!(format_version(Version::HTTP_11), "HTTP/1.1");
+        assert_eq!(format_version(Version::HTTP_2), "HTTP/2");
+    }
+}